@@ -18,4 +18,4 @@ use nrf52840_hal as hal;
 // pub mod i2s;
 pub mod pwm;
 
-pub use smart_leds_trait::RGB8;
+pub use smart_leds_trait::{RGB8, RGBW8};
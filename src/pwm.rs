@@ -8,61 +8,252 @@ use crate::hal::{
     pac,
 };
 
+use core::future::Future;
 use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::{Context, Poll, Waker};
 
-use smart_leds_trait::{SmartLedsWrite, RGB8};
+use smart_leds_trait::{SmartLedsWrite, RGB8, RGBW8};
 
-/// Fill a buffer with the DMA representation
+/// EasyDMA's maximum sequence length (the `SEQ[n].CNT` register is 15 bits wide)
+const MAX_SEQ_LEN: usize = 32767;
+
+/// Errors returned by the PWM Smartled driver
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The buffer is not located in RAM, so EasyDMA cannot read it.
+    ///
+    /// A common cause is passing a `static` color table, which the linker
+    /// places in flash.
+    BufferNotInRam,
+    /// The provided scratch space is smaller than required
+    ScratchTooSmall {
+        /// The number of u16s needed
+        needed: usize,
+        /// The number of u16s actually provided
+        got: usize,
+    },
+    /// The provided buffer was empty
+    EmptyBuffer,
+    /// The sequence is longer than EasyDMA's `SEQ[n].CNT` maximum
+    SequenceTooLong,
+    /// The provided strips did not all have the same length
+    MismatchedStripLengths,
+}
+
+/// Bit-timing and reset-gap configuration for a WS28xx/SK68xx-family LED
+/// chipset
+///
+/// `countertop` sets the PWM peripheral's bit period (in 16 MHz ticks,
+/// since the prescaler is fixed at div_1); `t1h_ticks`/`t0h_ticks` set how
+/// many of those ticks the line is held high to encode a logical `1`/`0`
+/// bit; `reset_slots` is the number of low (`0x8000`) u16 slots appended
+/// after the pixel data to hold the line low for the chipset's
+/// latch/reset window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Timing {
+    /// PWM COUNTERTOP: the bit period, in 16 MHz ticks
+    pub countertop: u16,
+    /// High time, in ticks, used to encode a logical `1` bit
+    pub t1h_ticks: u16,
+    /// High time, in ticks, used to encode a logical `0` bit
+    pub t0h_ticks: u16,
+    /// Number of low u16 slots appended after pixel data to hold the
+    /// line low for the chipset's reset/latch window
+    pub reset_slots: usize,
+}
+
+impl Timing {
+    /// WS2812/WS2812B: 800 kHz, 1.25 us bit period, >50 us reset
+    pub const WS2812: Timing = Timing {
+        countertop: 20,
+        t1h_ticks: 13,
+        t0h_ticks: 5,
+        reset_slots: 40,
+    };
+
+    /// SK6812/SK6812-RGBW: 800 kHz, 1.25 us bit period, >80 us reset
+    pub const SK6812: Timing = Timing {
+        countertop: 20,
+        t1h_ticks: 13,
+        t0h_ticks: 6,
+        reset_slots: 64,
+    };
+
+    /// WS2811 in "slow" (400 kHz) mode: 2.5 us bit period, >50 us reset
+    pub const WS2811_SLOW: Timing = Timing {
+        countertop: 40,
+        t1h_ticks: 24,
+        t0h_ticks: 10,
+        reset_slots: 20,
+    };
+
+    /// APA106: ~585 kHz, 1.71 us bit period (T0H 350 ns / T1H 1360 ns),
+    /// >50 us reset
+    pub const APA106: Timing = Timing {
+        countertop: 27,
+        t1h_ticks: 22,
+        t0h_ticks: 6,
+        reset_slots: 40,
+    };
+}
+
+/// Fill a buffer with the DMA representation, using `Timing::WS2812`
 ///
 /// The buffer must be a slice of 24 u16s or more.
-pub fn fill_buf(color: &RGB8, buf: &mut [u16]) -> Result<(), ()> {
+pub fn fill_buf(color: &RGB8, buf: &mut [u16]) -> Result<(), Error> {
+    fill_buf_timed(&Timing::WS2812, color, buf)
+}
+
+/// Fill a buffer with the DMA representation, using the compare values
+/// from a given `Timing`
+///
+/// The buffer must be a slice of 24 u16s or more.
+pub fn fill_buf_timed(timing: &Timing, color: &RGB8, buf: &mut [u16]) -> Result<(), Error> {
     if buf.len() < 24 {
-        return Err(());
+        return Err(Error::ScratchTooSmall {
+            needed: 24,
+            got: buf.len(),
+        });
     }
 
     let red = color.r.reverse_bits();
     let green = color.g.reverse_bits();
     let blue = color.b.reverse_bits();
 
+    let hi = 0x8000 | timing.t1h_ticks;
+    let lo = 0x8000 | timing.t0h_ticks;
+
     for g in 0..8 {
-        if ((green >> g) & 0b1) == 0b1 {
-            buf[g] = 0x8000 | 13;
-        } else {
-            buf[g] = 0x8000 | 5;
-        }
+        buf[g] = if ((green >> g) & 0b1) == 0b1 { hi } else { lo };
     }
 
     for r in 0..8 {
-        if ((red >> r) & 0b1) == 0b1 {
-            buf[8 + r] = 0x8000 | 13;
-        } else {
-            buf[8 + r] = 0x8000 | 5;
-        }
+        buf[8 + r] = if ((red >> r) & 0b1) == 0b1 { hi } else { lo };
     }
 
     for b in 0..8 {
-        if ((blue >> b) & 0b1) == 0b1 {
-            buf[16 + b] = 0x8000 | 13;
-        } else {
-            buf[16 + b] = 0x8000 | 5;
-        }
+        buf[16 + b] = if ((blue >> b) & 0b1) == 0b1 { hi } else { lo };
     }
 
     Ok(())
 }
 
+/// Compute the compare value for a single WS28xx bit (0..24, G-R-B order,
+/// MSB first) of `color`, using the timing from a given `Timing`
+///
+/// This is the per-bit equivalent of `fill_buf_timed`, used where the 24
+/// bits of one pixel aren't laid out contiguously (e.g. `PwmMulti`'s
+/// channel-interleaved scratch buffer).
+fn bit_compare_timed(timing: &Timing, color: &RGB8, bit: usize) -> u16 {
+    let hi = 0x8000 | timing.t1h_ticks;
+    let lo = 0x8000 | timing.t0h_ticks;
+
+    let (byte, idx) = if bit < 8 {
+        (color.g.reverse_bits(), bit)
+    } else if bit < 16 {
+        (color.r.reverse_bits(), bit - 8)
+    } else {
+        (color.b.reverse_bits(), bit - 16)
+    };
+
+    if ((byte >> idx) & 0b1) == 0b1 {
+        hi
+    } else {
+        lo
+    }
+}
+
+/// Fill a buffer with the DMA representation of an RGBW pixel, using
+/// `Timing::SK6812`
+///
+/// The buffer must be a slice of 32 u16s or more.
+pub fn fill_buf_rgbw(color: &RGBW8, buf: &mut [u16]) -> Result<(), Error> {
+    fill_buf_rgbw_timed(&Timing::SK6812, color, buf)
+}
+
+/// Fill a buffer with the DMA representation of an RGBW pixel (G-R-B-W,
+/// 32 bits), using the compare values from a given `Timing`
+///
+/// The buffer must be a slice of 32 u16s or more.
+pub fn fill_buf_rgbw_timed(timing: &Timing, color: &RGBW8, buf: &mut [u16]) -> Result<(), Error> {
+    if buf.len() < 32 {
+        return Err(Error::ScratchTooSmall {
+            needed: 32,
+            got: buf.len(),
+        });
+    }
+
+    let red = color.r.reverse_bits();
+    let green = color.g.reverse_bits();
+    let blue = color.b.reverse_bits();
+    let white = color.a.reverse_bits();
+
+    let hi = 0x8000 | timing.t1h_ticks;
+    let lo = 0x8000 | timing.t0h_ticks;
+
+    for g in 0..8 {
+        buf[g] = if ((green >> g) & 0b1) == 0b1 { hi } else { lo };
+    }
+
+    for r in 0..8 {
+        buf[8 + r] = if ((red >> r) & 0b1) == 0b1 { hi } else { lo };
+    }
+
+    for b in 0..8 {
+        buf[16 + b] = if ((blue >> b) & 0b1) == 0b1 { hi } else { lo };
+    }
+
+    for w in 0..8 {
+        buf[24 + w] = if ((white >> w) & 0b1) == 0b1 { hi } else { lo };
+    }
+
+    Ok(())
+}
+
+/// How many times a `Pwm::start_loop`ed sequence should be replayed
+pub enum LoopMode {
+    /// Play the sequence `n + 1` times, then stop automatically
+    ///
+    /// The LOOP register counts to at most 65535, so `Additional(u16::MAX)`
+    /// plays the sequence 65535 times.
+    Additional(u16),
+    /// Loop the sequence forever, until `Pwm::stop_loop` is called
+    Infinite,
+}
+
 /// A PWM peripheral driven Smartled driver
 pub struct Pwm<T: sealed::Instance> {
     pwm: T,
     _gpio: Pin<Output<PushPull>>,
+    waker: Option<Waker>,
+    done: bool,
+    timing: Timing,
 }
 
 impl<T> Pwm<T>
 where
     T: sealed::Instance,
 {
-    /// Create a new Smartled driver with a given pin and PWM engine
-    pub fn new<Mode>(pwm: T, pin: Pin<Mode>) -> Pwm<T> {
+    /// Create a new Smartled driver with a given pin, PWM engine, and
+    /// chipset `Timing` (e.g. `Timing::WS2812`)
+    pub fn new<Mode>(pwm: T, pin: Pin<Mode>, timing: Timing) -> Pwm<T> {
+        Self::new_inner(pwm, pin, false, timing)
+    }
+
+    /// Create a new Smartled driver with a given pin, PWM engine, and
+    /// chipset `Timing`, with the SEQEND[0]/SEQEND[1]/LOOPSDONE interrupt
+    /// sources enabled.
+    ///
+    /// Use this constructor together with `start_send`/`on_interrupt` to
+    /// drive a strip without busy-waiting for the transfer to complete.
+    /// The caller is still responsible for unmasking the PWM peripheral's
+    /// interrupt in the NVIC and for calling `Pwm::on_interrupt` from the
+    /// corresponding interrupt handler.
+    pub fn new_with_interrupts<Mode>(pwm: T, pin: Pin<Mode>, timing: Timing) -> Pwm<T> {
+        Self::new_inner(pwm, pin, true, timing)
+    }
+
+    fn new_inner<Mode>(pwm: T, pin: Pin<Mode>, interrupts: bool, timing: Timing) -> Pwm<T> {
         let pin = pin.into_push_pull_output(Level::Low);
 
         pwm.psel.out[0].write(|w| {
@@ -80,7 +271,8 @@ where
         pwm.enable.write(|w| w.enable().enabled());
         pwm.mode.write(|w| w.updown().up());
         pwm.prescaler.write(|w| w.prescaler().div_1());
-        pwm.countertop.write(|w| unsafe { w.countertop().bits(20) });
+        pwm.countertop
+            .write(|w| unsafe { w.countertop().bits(timing.countertop) });
         pwm.loop_.write(|w| w.cnt().disabled());
         pwm.decoder.write(|w| {
             w.load().common();
@@ -91,7 +283,21 @@ where
         pwm.seq1.refresh.write(|w| unsafe { w.bits(0) });
         pwm.seq1.enddelay.write(|w| unsafe { w.bits(0) });
 
-        Pwm { pwm, _gpio: pin }
+        if interrupts {
+            pwm.intenset.write(|w| {
+                w.seqend0().set_bit();
+                w.seqend1().set_bit();
+                w.loopsdone().set_bit()
+            });
+        }
+
+        Pwm {
+            pwm,
+            _gpio: pin,
+            waker: None,
+            done: false,
+            timing,
+        }
     }
 
     /// Start sending raw data
@@ -100,16 +306,19 @@ where
     ///
     /// SAFETY: the contents of `buf` must live and be constant until Pwm::is_done_raw()
     /// returns true.
-    pub unsafe fn start_send_raw(&mut self, buf: *const [u16]) -> Result<(), ()> {
-        // TODO: Check maximum supported len?
+    pub unsafe fn start_send_raw(&mut self, buf: *const [u16]) -> Result<(), Error> {
         if (*buf).is_empty() {
-            return Err(());
+            return Err(Error::EmptyBuffer);
+        }
+
+        if (*buf).len() > MAX_SEQ_LEN {
+            return Err(Error::SequenceTooLong);
         }
 
         if (((*buf).as_ptr() as usize) < hal::target_constants::SRAM_LOWER)
             || (((*buf).as_ptr() as usize) > hal::target_constants::SRAM_UPPER)
         {
-            return Err(());
+            return Err(Error::BufferNotInRam);
         }
 
         compiler_fence(Ordering::SeqCst);
@@ -126,16 +335,19 @@ where
     ///
     /// SAFETY: the contents of `buf` must live and me constant until sequence 1
     /// is completed
-    pub unsafe fn set_seq1_raw(&mut self, buf: *const [u16]) -> Result<(), ()> {
-        // TODO: Check maximum supported len?
+    pub unsafe fn set_seq1_raw(&mut self, buf: *const [u16]) -> Result<(), Error> {
         if (*buf).is_empty() {
-            return Err(());
+            return Err(Error::EmptyBuffer);
+        }
+
+        if (*buf).len() > MAX_SEQ_LEN {
+            return Err(Error::SequenceTooLong);
         }
 
         if (((*buf).as_ptr() as usize) < hal::target_constants::SRAM_LOWER)
             || (((*buf).as_ptr() as usize) > hal::target_constants::SRAM_UPPER)
         {
-            return Err(());
+            return Err(Error::BufferNotInRam);
         }
 
         compiler_fence(Ordering::SeqCst);
@@ -160,17 +372,62 @@ where
     ///
     /// NOTE: You can also use the SmartLedsWrite::write method to avoid the
     /// need for a scratch space (it uses its own)
-    pub fn send_full_buf(&mut self, colors: &[RGB8], scratch: &mut [u16]) -> Result<(), ()> {
-        if scratch.len() < u16s_needed_slice(colors) {
-            return Err(());
+    pub fn send_full_buf(&mut self, colors: &[RGB8], scratch: &mut [u16]) -> Result<(), Error> {
+        let needed = u16s_needed_slice(colors, &self.timing);
+        if scratch.len() < needed {
+            return Err(Error::ScratchTooSmall {
+                needed,
+                got: scratch.len(),
+            });
         }
 
         for (color, buf) in colors.iter().zip(scratch.chunks_exact_mut(24)) {
-            fill_buf(color, buf)?;
+            fill_buf_timed(&self.timing, color, buf)?;
         }
 
         let start = colors.len() * 24;
-        let end = start + 40;
+        let end = start + self.timing.reset_slots;
+
+        for by in &mut scratch[start..end] {
+            *by = 0x8000;
+        }
+
+        // Disable looping, this is a one-shot
+        self.pwm.loop_.write(|w| w.cnt().disabled());
+
+        // Safety: we block until the DMA transaction is complete
+        unsafe {
+            self.start_send_raw(&scratch[..end])?;
+        }
+        while !self.is_done_raw() {}
+
+        Ok(())
+    }
+
+    /// Send a series of RGBW colors and a stop pattern, using a given
+    /// scratch space
+    ///
+    /// NOTE: the size of `scratch` must be >= u16s_needed_rgbw_slice(colors, &timing),
+    /// where `timing` is the `Timing` this driver was created with.
+    pub fn send_full_buf_rgbw(
+        &mut self,
+        colors: &[RGBW8],
+        scratch: &mut [u16],
+    ) -> Result<(), Error> {
+        let needed = u16s_needed_rgbw_slice(colors, &self.timing);
+        if scratch.len() < needed {
+            return Err(Error::ScratchTooSmall {
+                needed,
+                got: scratch.len(),
+            });
+        }
+
+        for (color, buf) in colors.iter().zip(scratch.chunks_exact_mut(32)) {
+            fill_buf_rgbw_timed(&self.timing, color, buf)?;
+        }
+
+        let start = colors.len() * 32;
+        let end = start + self.timing.reset_slots;
 
         for by in &mut scratch[start..end] {
             *by = 0x8000;
@@ -187,30 +444,402 @@ where
 
         Ok(())
     }
+
+    /// Start sending a series of colors without blocking, using a given
+    /// scratch space, and return a future that resolves once the transfer
+    /// is complete.
+    ///
+    /// NOTE: the size of `scratch` must be >= u16s_needed_slice(colors, &timing),
+    /// where `timing` is the `Timing` this driver was created with.
+    ///
+    /// NOTE: this requires interrupts to be enabled (see
+    /// `Pwm::new_with_interrupts`) and `Pwm::on_interrupt` to be called
+    /// from the PWM interrupt handler, or the returned future will never
+    /// resolve.
+    pub fn start_send<'s>(
+        &'s mut self,
+        colors: &[RGB8],
+        scratch: &'s mut [u16],
+    ) -> Result<SendFuture<'s, T>, Error> {
+        let needed = u16s_needed_slice(colors, &self.timing);
+        if scratch.len() < needed {
+            return Err(Error::ScratchTooSmall {
+                needed,
+                got: scratch.len(),
+            });
+        }
+
+        for (color, buf) in colors.iter().zip(scratch.chunks_exact_mut(24)) {
+            fill_buf_timed(&self.timing, color, buf)?;
+        }
+
+        let start = colors.len() * 24;
+        let end = start + self.timing.reset_slots;
+
+        for by in &mut scratch[start..end] {
+            *by = 0x8000;
+        }
+
+        // Disable looping, this is a one-shot
+        self.pwm.loop_.write(|w| w.cnt().disabled());
+        self.done = false;
+
+        // Safety: the caller holds `scratch` borrowed (via the returned
+        // SendFuture) until the transfer is observed complete through
+        // `poll_done`/`on_interrupt`.
+        unsafe {
+            self.start_send_raw(&scratch[..end])?;
+        }
+
+        Ok(SendFuture { pwm: self })
+    }
+
+    /// Start hardware-looped playback of a pre-rendered sequence
+    ///
+    /// Unlike `send_full_buf`/`start_send`, the repeat count is handed
+    /// entirely to the PWM peripheral's LOOP register: once kicked off,
+    /// `buf` is replayed `times` playbacks with zero further CPU or DMA
+    /// involvement, making this suitable for a steady color or a looping
+    /// animation frame held in SRAM.
+    ///
+    /// `buf` should already contain the rendered LED data and a trailing
+    /// reset/stop pad (see `fill_buf`/`u16s_needed_slice`).
+    ///
+    /// SAFETY: the contents of `buf` must live and be constant until
+    /// `Pwm::stop_loop` is called.
+    pub unsafe fn start_loop(&mut self, buf: *const [u16], times: LoopMode) -> Result<(), Error> {
+        if (*buf).is_empty() {
+            return Err(Error::EmptyBuffer);
+        }
+
+        if (*buf).len() > MAX_SEQ_LEN {
+            return Err(Error::SequenceTooLong);
+        }
+
+        if (((*buf).as_ptr() as usize) < hal::target_constants::SRAM_LOWER)
+            || (((*buf).as_ptr() as usize) > hal::target_constants::SRAM_UPPER)
+        {
+            return Err(Error::BufferNotInRam);
+        }
+
+        // LOOP.CNT is a finite 16-bit counter, so it cannot express true
+        // infinite looping on its own (it would stop after 65535 loops).
+        // For `Infinite`, keep the count at its minimum non-zero value (a
+        // count of zero disables LOOPSDONE entirely) and use the
+        // LOOPSDONE->SEQSTART0 shortcut so the peripheral restarts the
+        // sequence pair itself, forever, with no CPU involvement, until
+        // `stop_loop` is called.
+        let cnt = match times {
+            LoopMode::Additional(n) => n.saturating_add(1),
+            LoopMode::Infinite => 1,
+        };
+        self.pwm.loop_.write(|w| w.cnt().bits(cnt));
+
+        if matches!(times, LoopMode::Infinite) {
+            self.pwm.shorts.write(|w| w.loopsdone_seqstart0().set_bit());
+        } else {
+            self.pwm.shorts.reset();
+        }
+
+        compiler_fence(Ordering::SeqCst);
+
+        // LOOP repeats the SEQ0/SEQ1 pair, so SEQ1 must be explicitly
+        // cleared -- otherwise it could still be holding a buffer/length
+        // left over from a previous `SmartLedsWrite::write` call, and the
+        // pair would play that back too. A CNT of zero makes SEQ1
+        // contribute nothing to the pair, so one pair plays `buf` exactly
+        // once, and the `n + 1` pairs of `LoopMode::Additional(n)` play it
+        // `n + 1` times.
+        self.pwm.seq0.ptr.write(|w| w.bits((*buf).as_ptr() as u32));
+        self.pwm.seq0.cnt.write(|w| w.bits((*buf).len() as u32));
+        self.pwm.seq1.cnt.write(|w| w.bits(0));
+        self.pwm.events_seqend[0].write(|w| w.bits(0));
+        self.pwm.events_seqend[1].write(|w| w.bits(0));
+        self.pwm.events_loopsdone.write(|w| w.bits(0));
+        self.pwm.tasks_seqstart[0].write(|w| w.bits(1));
+
+        Ok(())
+    }
+
+    /// Stop a playback started with `start_loop`
+    ///
+    /// Halts the PWM peripheral via the STOP task, disables looping, and
+    /// clears the LOOPSDONE->SEQSTART0 shortcut used by
+    /// `LoopMode::Infinite`, leaving the driver ready for a subsequent
+    /// one-shot `send_full_buf`/`start_send`.
+    pub fn stop_loop(&mut self) {
+        self.pwm.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.pwm.shorts.reset();
+        self.pwm.loop_.write(|w| w.cnt().disabled());
+    }
+
+    /// Is a `start_send` transfer complete?
+    ///
+    /// This is a non-blocking check; most users should prefer awaiting
+    /// the `SendFuture` returned by `start_send` instead of polling this
+    /// directly.
+    ///
+    /// NOTE: this reflects the latched `done` flag set by `on_interrupt`,
+    /// not the raw SEQEND[0] event (which `on_interrupt` clears on the
+    /// way through) -- see `Pwm::is_done_raw` for the raw event.
+    pub fn poll_done(&self) -> bool {
+        self.done
+    }
+
+    /// Interrupt handler entry point.
+    ///
+    /// Call this from the PWM peripheral's interrupt handler. It clears
+    /// the SEQEND[0]/SEQEND[1]/LOOPSDONE event flags, latches completion
+    /// of a `start_send` transfer (see `poll_done`), and wakes the waker
+    /// stored by a pending `SendFuture`, if any.
+    pub fn on_interrupt(&mut self) {
+        if self.pwm.events_seqend[0].read().bits() == 1 {
+            self.pwm.events_seqend[0].write(|w| unsafe { w.bits(0) });
+            self.done = true;
+        }
+        if self.pwm.events_seqend[1].read().bits() == 1 {
+            self.pwm.events_seqend[1].write(|w| unsafe { w.bits(0) });
+        }
+        if self.pwm.events_loopsdone.read().bits() == 1 {
+            self.pwm.events_loopsdone.write(|w| unsafe { w.bits(0) });
+        }
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A Smartled driver that drives up to four independent strips in
+/// parallel from a single PWM peripheral, using its four output channels
+/// and the Individual decoder load mode
+pub struct PwmMulti<T: sealed::Instance, const N: usize> {
+    pwm: T,
+    _gpio: [Pin<Output<PushPull>>; N],
+    timing: Timing,
+}
+
+impl<T, const N: usize> PwmMulti<T, N>
+where
+    T: sealed::Instance,
+{
+    /// Create a new multi-strip Smartled driver, wiring between one and
+    /// four pins to the PWM peripheral's output channels
+    pub fn new<Mode>(pwm: T, pins: [Pin<Mode>; N], timing: Timing) -> PwmMulti<T, N> {
+        assert!((1..=4).contains(&N), "PwmMulti supports 1 to 4 channels");
+
+        let pins = pins.map(|pin| pin.into_push_pull_output(Level::Low));
+
+        for (idx, pin) in pins.iter().enumerate() {
+            pwm.psel.out[idx].write(|w| {
+                #[cfg(feature = "52840")]
+                match pin.port() {
+                    hal::gpio::Port::Port0 => w.port().clear_bit(),
+                    hal::gpio::Port::Port1 => w.port().set_bit(),
+                };
+                unsafe {
+                    w.pin().bits(pin.pin());
+                }
+                w.connect().connected()
+            });
+        }
+
+        pwm.enable.write(|w| w.enable().enabled());
+        pwm.mode.write(|w| w.updown().up());
+        pwm.prescaler.write(|w| w.prescaler().div_1());
+        pwm.countertop
+            .write(|w| unsafe { w.countertop().bits(timing.countertop) });
+        pwm.loop_.write(|w| w.cnt().disabled());
+        pwm.decoder.write(|w| {
+            w.load().individual();
+            w.mode().refresh_count()
+        });
+        pwm.seq0.refresh.write(|w| unsafe { w.bits(0) });
+        pwm.seq0.enddelay.write(|w| unsafe { w.bits(0) });
+
+        PwmMulti {
+            pwm,
+            _gpio: pins,
+            timing,
+        }
+    }
+
+    /// Send one frame to each of the `N` strips in parallel, using a
+    /// given scratch space
+    ///
+    /// Individual decoder load mode always consumes four half-words per
+    /// bit-period -- one per physical channel CH0-CH3 -- regardless of how
+    /// many of them are actually wired up. So each pixel's compare values
+    /// are interleaved across all four channels (`[ch0_0, ch1_0, ch2_0,
+    /// ch3_0, ch0_1, ...]`), with any channel beyond `N` padded with a
+    /// constant low slot, and streamed out through a single DMA transfer
+    /// that refreshes every connected strip at once. All `strips` must be
+    /// the same length.
+    ///
+    /// NOTE: the size of `scratch` must be >=
+    /// `u16s_needed_multi_ct(leds, timing.reset_slots)`.
+    pub fn send_full_buf_multi(
+        &mut self,
+        strips: [&[RGB8]; N],
+        scratch: &mut [u16],
+    ) -> Result<(), Error> {
+        let leds = strips[0].len();
+        if strips.iter().any(|strip| strip.len() != leds) {
+            return Err(Error::MismatchedStripLengths);
+        }
+
+        let needed = u16s_needed_multi_ct(leds, self.timing.reset_slots);
+        if scratch.len() < needed {
+            return Err(Error::ScratchTooSmall {
+                needed,
+                got: scratch.len(),
+            });
+        }
+
+        // Individual mode loads 4 consecutive half-words into COMP[0..3]
+        // every single PWM period, one per physical channel -- not 24
+        // consecutive values for one channel followed by 24 for the next.
+        // So each pixel's 24 bits must be scattered across the buffer
+        // strided by 4, not packed into a contiguous per-channel block.
+        for i in 0..leds {
+            for bit in 0..24 {
+                for ch in 0..4 {
+                    let compare = match strips.get(ch) {
+                        Some(strip) => bit_compare_timed(&self.timing, &strip[i], bit),
+                        None => 0x8000,
+                    };
+                    scratch[i * 96 + bit * 4 + ch] = compare;
+                }
+            }
+        }
+
+        let start = leds * 24 * 4;
+        let end = start + self.timing.reset_slots * 4;
+
+        for by in &mut scratch[start..end] {
+            *by = 0x8000;
+        }
+
+        let scratch = &scratch[..end];
+
+        if scratch.len() > MAX_SEQ_LEN {
+            return Err(Error::SequenceTooLong);
+        }
+
+        if ((scratch.as_ptr() as usize) < hal::target_constants::SRAM_LOWER)
+            || ((scratch.as_ptr() as usize) > hal::target_constants::SRAM_UPPER)
+        {
+            return Err(Error::BufferNotInRam);
+        }
+
+        // Disable looping, this is a one-shot
+        self.pwm.loop_.write(|w| w.cnt().disabled());
+
+        compiler_fence(Ordering::SeqCst);
+
+        self.pwm.seq0.ptr.write(|w| unsafe { w.bits(scratch.as_ptr() as u32) });
+        self.pwm.seq0.cnt.write(|w| unsafe { w.bits(scratch.len() as u32) });
+        self.pwm.events_seqend[0].write(|w| unsafe { w.bits(0) });
+        self.pwm.tasks_seqstart[0].write(|w| unsafe { w.bits(1) });
+
+        while self.pwm.events_seqend[0].read().bits() == 0 {}
+
+        Ok(())
+    }
 }
 
-/// How many u16s are needed to send a given slice
+/// How many u16s are needed to drive a `PwmMulti` of `leds` pixels each,
+/// given a chipset's reset/stop pattern length (`Timing::reset_slots`)
 ///
-/// This number includes space for necessary stop patterns
-pub fn u16s_needed_slice(slice: &[RGB8]) -> usize {
-    u16s_needed_ct(slice.len())
+/// Individual decoder load mode always consumes 4 channels' worth of
+/// compare values per bit-period, regardless of how many of `PwmMulti`'s
+/// channels are actually wired to a strip. For the same reason, the reset
+/// pad takes `reset_slots * 4` u16s to hold the line low for
+/// `reset_slots` bit periods.
+pub const fn u16s_needed_multi_ct(leds: usize, reset_slots: usize) -> usize {
+    leds * 24 * 4 + reset_slots * 4
+}
+
+/// A future returned by `Pwm::start_send`, resolving once the transfer it
+/// started has completed.
+pub struct SendFuture<'a, T: sealed::Instance> {
+    pwm: &'a mut Pwm<T>,
+}
+
+impl<'a, T> Future for SendFuture<'a, T>
+where
+    T: sealed::Instance,
+{
+    type Output = ();
+
+    fn poll(self: core::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Register the waker *before* checking `done`, so that an
+        // `on_interrupt` landing between the two can't wake a stale
+        // waker while leaving this freshly-registered one never woken.
+        let this = self.get_mut();
+        this.pwm.waker = Some(cx.waker().clone());
+
+        if this.pwm.poll_done() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
 }
 
-/// How many u16s are needed to send a given number of RGB8s
+impl<'a, T> Drop for SendFuture<'a, T>
+where
+    T: sealed::Instance,
+{
+    fn drop(&mut self) {
+        // If the transfer hasn't completed, the scratch buffer borrowed by
+        // `start_send` is about to be released while EasyDMA may still be
+        // reading it. Halt the peripheral and wait for it to go idle
+        // before that happens.
+        if !self.pwm.poll_done() {
+            self.pwm.pwm.tasks_stop.write(|w| unsafe { w.bits(1) });
+            while self.pwm.pwm.events_stopped.read().bits() == 0 {}
+            self.pwm.pwm.events_stopped.write(|w| unsafe { w.bits(0) });
+            self.pwm.done = true;
+        }
+    }
+}
+
+/// How many u16s are needed to send a given slice, for a given `Timing`
 ///
-/// This number includes space for necessary stop patterns
-pub const fn u16s_needed_ct(leds: usize) -> usize {
-    leds * 24 + 40
+/// This number includes space for the chipset's reset/stop pattern
+pub fn u16s_needed_slice(slice: &[RGB8], timing: &Timing) -> usize {
+    u16s_needed_ct(slice.len(), timing.reset_slots)
+}
+
+/// How many u16s are needed to send a given number of RGB8s, given a
+/// chipset's reset/stop pattern length (`Timing::reset_slots`)
+pub const fn u16s_needed_ct(leds: usize, reset_slots: usize) -> usize {
+    leds * 24 + reset_slots
+}
+
+/// How many u16s are needed to send a given slice of RGBW8s, for a given
+/// `Timing`
+///
+/// This number includes space for the chipset's reset/stop pattern
+pub fn u16s_needed_rgbw_slice(slice: &[RGBW8], timing: &Timing) -> usize {
+    u16s_needed_rgbw_ct(slice.len(), timing.reset_slots)
+}
+
+/// How many u16s are needed to send a given number of RGBW8s, given a
+/// chipset's reset/stop pattern length (`Timing::reset_slots`)
+pub const fn u16s_needed_rgbw_ct(leds: usize, reset_slots: usize) -> usize {
+    leds * 32 + reset_slots
 }
 
 impl<T> SmartLedsWrite for Pwm<T>
 where
     T: sealed::Instance,
 {
-    type Error = ();
+    type Error = Error;
     type Color = RGB8;
     /// Write all the items of an iterator to a ws2812 strip
-    fn write<Iter, I>(&mut self, mut iterator: Iter) -> Result<(), ()>
+    fn write<Iter, I>(&mut self, mut iterator: Iter) -> Result<(), Error>
     where
         Iter: Iterator<Item = I>,
         I: Into<Self::Color>,
@@ -225,18 +854,18 @@ where
         match (iterator.next(), iterator.next()) {
             (Some(a), Some(b)) => {
                 // Two pixels, fill two buffers
-                fill_buf(&a.into(), &mut buf_a)?;
-                fill_buf(&b.into(), &mut buf_b)?;
+                fill_buf_timed(&self.timing, &a.into(), &mut buf_a)?;
+                fill_buf_timed(&self.timing, &b.into(), &mut buf_b)?;
             }
             (Some(a), None) => {
                 // One pixel, fill the pixel and a blank
-                fill_buf(&a.into(), &mut buf_a)?;
+                fill_buf_timed(&self.timing, &a.into(), &mut buf_a)?;
                 buf_b.copy_from_slice(&[0x8000u16; 24]);
                 blanks_fed = 1;
             }
             (None, Some(_)) => {
                 // what? Intermittent iterator?
-                return Err(());
+                return Err(Error::EmptyBuffer);
             }
             _ => {
                 // Empty iterator, nothing completed successfully
@@ -283,7 +912,7 @@ where
                 // refill seq[0] data
                 match seq {
                     Data::Pixel(p) => {
-                        fill_buf(&p, &mut buf_a)?;
+                        fill_buf_timed(&self.timing, &p, &mut buf_a)?;
                     }
                     Data::Blank => {
                         buf_a.copy_from_slice(&[0x8000u16; 24]);
@@ -322,7 +951,7 @@ where
                 // refill seq[1] data
                 match seq {
                     Data::Pixel(p) => {
-                        fill_buf(&p, &mut buf_b)?;
+                        fill_buf_timed(&self.timing, &p, &mut buf_b)?;
                     }
                     Data::Blank => {
                         buf_b.copy_from_slice(&[0x8000u16; 24]);